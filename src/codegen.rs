@@ -0,0 +1,73 @@
+//! Compiles a token stream to x86-64 NASM assembly instead of interpreting it.
+
+use crate::Token;
+
+/// Emits a standalone NASM source that can be assembled with `nasm` and
+/// linked with `ld`. The generated program keeps the cell pointer in `rdx`
+/// for the whole run; `.`/`,` stash it across the syscall they issue since
+/// the syscall ABI also wants `rdx` for the transfer length.
+pub fn generate(tokens: &[Token]) -> Result<String, String> {
+    let mut asm = String::new();
+    asm.push_str("section .bss\n");
+    asm.push_str("data: resb 65536\n\n");
+    asm.push_str("section .text\n");
+    asm.push_str("global _start\n");
+    asm.push_str("_start:\n");
+    asm.push_str("    mov rdx, data\n");
+
+    let mut loop_stack = Vec::new();
+    let mut next_loop_id = 0;
+
+    for token in tokens {
+        match token {
+            Token::Plus(count) => asm.push_str(&format!("    add byte [rdx], {count}\n")),
+            Token::Minus(count) => asm.push_str(&format!("    sub byte [rdx], {count}\n")),
+            Token::Right(count) => asm.push_str(&format!("    add rdx, {count}\n")),
+            Token::Left(count) => asm.push_str(&format!("    sub rdx, {count}\n")),
+            Token::Dot => {
+                asm.push_str("    push rdx\n");
+                asm.push_str("    mov rax, 1\n");
+                asm.push_str("    mov rdi, 1\n");
+                asm.push_str("    mov rsi, rdx\n");
+                asm.push_str("    mov rdx, 1\n");
+                asm.push_str("    syscall\n");
+                asm.push_str("    pop rdx\n");
+            }
+            Token::Comma => {
+                asm.push_str("    push rdx\n");
+                asm.push_str("    mov rax, 0\n");
+                asm.push_str("    mov rdi, 0\n");
+                asm.push_str("    mov rsi, rdx\n");
+                asm.push_str("    mov rdx, 1\n");
+                asm.push_str("    syscall\n");
+                asm.push_str("    pop rdx\n");
+            }
+            Token::BracketOpen => {
+                let id = next_loop_id;
+                next_loop_id += 1;
+                loop_stack.push(id);
+                asm.push_str(&format!("loop_start_{id}:\n"));
+                asm.push_str("    cmp byte [rdx], 0\n");
+                asm.push_str(&format!("    je loop_end_{id}\n"));
+            }
+            Token::BracketClose => {
+                let id = loop_stack
+                    .pop()
+                    .ok_or_else(|| "Unmatched ']' in program".to_string())?;
+                asm.push_str("    cmp byte [rdx], 0\n");
+                asm.push_str(&format!("    jne loop_start_{id}\n"));
+                asm.push_str(&format!("loop_end_{id}:\n"));
+            }
+        }
+    }
+
+    if !loop_stack.is_empty() {
+        return Err("Unmatched '[': missing closing bracket".to_string());
+    }
+
+    asm.push_str("    mov rax, 60\n");
+    asm.push_str("    xor rdi, rdi\n");
+    asm.push_str("    syscall\n");
+
+    return Ok(asm);
+}
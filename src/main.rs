@@ -1,88 +1,161 @@
 use std::io::Read;
 
+mod codegen;
+mod parser;
+
+use parser::Statement;
+
 fn main() {
     let args = std::env::args().collect::<Vec<String>>();
     let file_path = args.get(1).expect("No filename provided");
 
+    let mut compile = false;
+    let mut chunked = false;
+    let mut options = ExecutionOptions::default();
+    let mut flags = args.iter().skip(2);
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--compile" => compile = true,
+            "--chunked" => chunked = true,
+            "--dump" => options.dump = true,
+            "--trace" => options.trace = true,
+            "--max-steps" => {
+                let value = flags.next().expect("--max-steps requires a value");
+                options.max_steps = Some(value.parse().expect("--max-steps value must be a number"));
+            }
+            other => panic!("Unknown flag: {other}"),
+        }
+    }
+
     let text = std::fs::read_to_string(file_path).expect("File not found");
     let lexer = Tokenizer::new(text);
-
     let tokens: Vec<Token> = lexer.tokenize();
-    let cell_count = tokens
-        .iter()
-        .filter(|token| matches!(token, Token::Plus))
-        .count();
 
-    let memory = Memory::new(cell_count);
-    let mut brain = Brain::new(tokens, memory);
+    if compile {
+        let asm = codegen::generate(&tokens).expect("Invalid program");
+        let output_path = format!("{file_path}.asm");
+        std::fs::write(&output_path, asm).expect("Failed to write assembly output");
+        return;
+    }
+
+    let program = parser::parse(&tokens).expect("Invalid program");
+    let memory = if chunked {
+        Memory::new_chunked()
+    } else {
+        Memory::new()
+    };
+    let mut brain = Brain::new(memory, options);
 
-    brain.run();
+    brain.run(&program).expect("Program failed");
+}
+
+/// Diagnostics toggled from the CLI that the default run leaves off.
+#[derive(Default)]
+struct ExecutionOptions {
+    dump: bool,
+    trace: bool,
+    max_steps: Option<u64>,
 }
 
 struct Brain {
-    program_counter: usize,
-    instructions: Vec<Token>,
     address_pointer: usize,
     memory: Memory,
+    options: ExecutionOptions,
+    steps: u64,
 }
 
 impl Brain {
-    fn new(instructions: Vec<Token>, memory: Memory) -> Brain {
+    fn new(memory: Memory, options: ExecutionOptions) -> Brain {
         return Brain {
-            program_counter: 0,
-            instructions,
             address_pointer: 0,
             memory,
+            options,
+            steps: 0,
         };
     }
 
-    fn run(&mut self) {
-        while self.program_counter < self.instructions.len() {
-            self.tick();
+    fn run(&mut self, program: &[Statement]) -> Result<(), String> {
+        self.run_block(program)?;
+        if self.options.dump {
+            println!("{}", self.memory.dump(self.address_pointer));
         }
+        return Ok(());
     }
 
-    fn tick(&mut self) {
-        let instruction = self
-            .instructions
-            .get(self.program_counter)
-            .expect("No instruction found at this index");
-        match instruction {
-            Token::AngleBracketOpen => self.move_left(),
-            Token::AngleBracketClose => self.move_right(),
-            Token::Plus => self.increment(),
-            Token::Minus => self.decrement(),
-            Token::Dot => self.output(),
-            Token::Comma => self.input(),
-            Token::BracketOpen => self.loop_start(),
-            Token::BracketClose => self.loop_end(),
+    fn run_block(&mut self, statements: &[Statement]) -> Result<(), String> {
+        for statement in statements {
+            self.execute(statement)?;
         }
+        return Ok(());
+    }
+
+    fn execute(&mut self, statement: &Statement) -> Result<(), String> {
+        self.count_step(&format!("{statement:?}"))?;
 
-        self.program_counter += 1;
+        match statement {
+            Statement::DecrementPointer(count) => self.move_left(*count)?,
+            Statement::IncrementPointer(count) => self.move_right(*count)?,
+            Statement::Increment(count) => self.increment(*count),
+            Statement::Decrement(count) => self.decrement(*count),
+            Statement::Output => self.output(),
+            Statement::Input => self.input(),
+            Statement::Loop(body) => {
+                while self.memory.get(self.address_pointer) != 0 {
+                    self.run_block(body)?;
+                    self.count_step("Loop condition re-check")?;
+                }
+            }
+        }
+        return Ok(());
     }
 
-    fn move_left(&mut self) {
-        self.address_pointer -= 1;
-        if self.address_pointer > self.memory.len() {
-            panic!("Out of bounds");
+    /// Counts one tick of execution and enforces `--max-steps`. Called once
+    /// per statement and again on every re-check of a `Loop`'s condition, so
+    /// an infinite loop with an empty body (`+[]`) still advances the count
+    /// instead of spinning forever without ever calling `execute` again.
+    /// `trace_label` is whatever should show up in `--trace` for this tick —
+    /// the statement's `Debug` form for a normal step, or a short note for a
+    /// loop re-check, which would otherwise reprint the whole loop body.
+    fn count_step(&mut self, trace_label: &str) -> Result<(), String> {
+        self.steps += 1;
+        if let Some(max_steps) = self.options.max_steps {
+            if self.steps > max_steps {
+                return Err(format!("Exceeded max steps ({max_steps})"));
+            }
         }
+        if self.options.trace {
+            eprintln!(
+                "step {} pointer {} cell {} : {}",
+                self.steps,
+                self.address_pointer,
+                self.memory.get(self.address_pointer),
+                trace_label
+            );
+        }
+        return Ok(());
     }
-    fn move_right(&mut self) {
-        self.address_pointer += 1;
-        if self.address_pointer > self.memory.len() {
-            panic!("Out of bounds");
+
+    fn move_left(&mut self, count: usize) -> Result<(), String> {
+        if count > self.address_pointer {
+            return Err("Out of bounds: cannot move left of cell 0".to_string());
         }
+        self.address_pointer -= count;
+        return Ok(());
+    }
+    fn move_right(&mut self, count: usize) -> Result<(), String> {
+        self.address_pointer += count;
+        return Ok(());
     }
-    fn increment(&mut self) {
+    fn increment(&mut self, count: u8) {
         self.memory.set(
             self.address_pointer,
-            self.memory.get(self.address_pointer) + 1,
+            self.memory.get(self.address_pointer).wrapping_add(count),
         );
     }
-    fn decrement(&mut self) {
+    fn decrement(&mut self, count: u8) {
         self.memory.set(
             self.address_pointer,
-            self.memory.get(self.address_pointer) - 1,
+            self.memory.get(self.address_pointer).wrapping_sub(count),
         );
     }
     fn output(&mut self) {
@@ -98,96 +171,116 @@ impl Brain {
                 .expect("No input"),
         );
     }
-    fn loop_start(&mut self) {
-        if self.memory.get(self.address_pointer) == 0 {
-            self.program_counter = self
-                .find_matching_closing()
-                .expect("No matching closing bracket found");
-        }
-    }
-    fn loop_end(&mut self) {
-        if self.memory.get(self.address_pointer) != 0 {
-            self.program_counter = self
-                .find_matching_opening()
-                .expect("No matching opening bracket found");
-        }
-    }
+}
 
-    fn find_matching_closing(&self) -> Option<usize> {
-        let mut open_brackets = 0;
-        for (index, token) in self
-            .instructions
-            .iter()
-            .enumerate()
-            .skip(self.program_counter)
-        {
-            match token {
-                Token::BracketOpen => open_brackets += 1,
-                Token::BracketClose => {
-                    if open_brackets == 0 {
-                        return Some(index);
-                    }
-                    open_brackets -= 1;
-                }
-                _ => {}
-            }
-        }
-        return None;
-    }
-    fn find_matching_opening(&self) -> Option<usize> {
-        let mut close_brackets = 0;
-        for (index, token) in self
-            .instructions
-            .iter()
-            .enumerate()
-            .take(self.program_counter)
-            .rev()
-        {
-            match token {
-                Token::BracketOpen => {
-                    if close_brackets == 0 {
-                        return Some(index);
-                    }
-                    close_brackets -= 1;
-                }
-                Token::BracketClose => close_brackets += 1,
-                _ => {}
-            }
-        }
-        return None;
+/// Size of a block in the `Chunked` backend.
+const BLOCK_SIZE: usize = 4096;
+
+/// A lazily-allocated block of the `Chunked` backend. `written` tracks which
+/// offsets were actually set, so `--dump` lists only those instead of
+/// spilling all `BLOCK_SIZE` entries of every allocated block.
+struct ChunkedBlock {
+    cells: Box<[u8; BLOCK_SIZE]>,
+    written: std::collections::HashSet<usize>,
+}
+
+impl ChunkedBlock {
+    fn new() -> ChunkedBlock {
+        return ChunkedBlock {
+            cells: Box::new([0; BLOCK_SIZE]),
+            written: std::collections::HashSet::new(),
+        };
     }
 }
 
-struct Memory {
-    cells: Vec<u8>,
+/// The Brainfuck tape. `Growing` is a flat `Vec<u8>` that extends on demand,
+/// giving the conceptual infinite right-hand tape the language assumes.
+/// `Chunked` trades that simplicity for memory efficiency when the pointer
+/// roams far and sparsely: cells are grouped into fixed-size blocks that are
+/// only allocated once something inside them is touched.
+enum Memory {
+    Growing(Vec<u8>),
+    Chunked(std::collections::HashMap<usize, ChunkedBlock>),
 }
 
 impl Memory {
-    fn new(size: usize) -> Memory {
-        return Memory {
-            cells: vec![0; size],
-        };
+    fn new() -> Memory {
+        return Memory::Growing(Vec::new());
+    }
+
+    fn new_chunked() -> Memory {
+        return Memory::Chunked(std::collections::HashMap::new());
     }
 
     fn get(&self, index: usize) -> u8 {
-        return self.cells[index];
+        match self {
+            Memory::Growing(cells) => cells.get(index).copied().unwrap_or(0),
+            Memory::Chunked(blocks) => {
+                let (block, offset) = Memory::locate(index);
+                return blocks.get(&block).map(|b| b.cells[offset]).unwrap_or(0);
+            }
+        }
     }
 
     fn set(&mut self, index: usize, value: u8) {
-        self.cells[index] = value;
+        match self {
+            Memory::Growing(cells) => {
+                if index >= cells.len() {
+                    cells.resize(index + 1, 0);
+                }
+                cells[index] = value;
+            }
+            Memory::Chunked(blocks) => {
+                let (block, offset) = Memory::locate(index);
+                let entry = blocks.entry(block).or_insert_with(ChunkedBlock::new);
+                entry.cells[offset] = value;
+                entry.written.insert(offset);
+            }
+        }
     }
 
-    fn len(&self) -> usize {
-        return self.cells.len();
+    fn locate(index: usize) -> (usize, usize) {
+        return (index / BLOCK_SIZE, index % BLOCK_SIZE);
+    }
+
+    /// Renders the tape as `index: value` lines, for `--dump`. `Growing` lists
+    /// every cell from 0 up to the highest one written (unwritten cells in
+    /// between show as 0); `Chunked` lists only the cells that were written.
+    fn dump(&self, pointer: usize) -> String {
+        let mut output = format!("pointer: {pointer}\n");
+        match self {
+            Memory::Growing(cells) => {
+                for (index, value) in cells.iter().enumerate() {
+                    output.push_str(&format!("{index}: {value}\n"));
+                }
+            }
+            Memory::Chunked(blocks) => {
+                let mut block_indices: Vec<usize> = blocks.keys().copied().collect();
+                block_indices.sort();
+                for block in block_indices {
+                    let entry = &blocks[&block];
+                    let mut offsets: Vec<usize> = entry.written.iter().copied().collect();
+                    offsets.sort();
+                    for offset in offsets {
+                        output.push_str(&format!(
+                            "{}: {}\n",
+                            block * BLOCK_SIZE + offset,
+                            entry.cells[offset]
+                        ));
+                    }
+                }
+            }
+        }
+        return output;
     }
 }
 
 #[derive(Debug)]
 enum Token {
-    AngleBracketOpen,
-    AngleBracketClose,
-    Plus,
-    Minus,
+    Left(usize),
+    Right(usize),
+    Plus(u8),
+    Minus(u8),
     Dot,
     Comma,
     BracketOpen,
@@ -204,21 +297,56 @@ impl Tokenizer {
         return Tokenizer { input: chars };
     }
 
+    /// Emits one token per run of identical characters instead of one per
+    /// character, so e.g. `++++++++` becomes a single `Plus(8)`. A `+`/`-`
+    /// run longer than `u8::MAX` is split across several tokens.
     fn tokenize(&self) -> Vec<Token> {
-        return self
-            .input
-            .iter()
-            .filter_map(|char| match char {
-                '<' => Some(Token::AngleBracketOpen),
-                '>' => Some(Token::AngleBracketClose),
-                '+' => Some(Token::Plus),
-                '-' => Some(Token::Minus),
-                '.' => Some(Token::Dot),
-                ',' => Some(Token::Comma),
-                '[' => Some(Token::BracketOpen),
-                ']' => Some(Token::BracketClose),
-                _ => None,
-            })
-            .collect();
+        let mut tokens = Vec::new();
+        let mut chars = self.input.iter().peekable();
+
+        while let Some(char) = chars.next() {
+            match char {
+                '<' => tokens.push(Token::Left(1 + Tokenizer::count_run(&mut chars, '<'))),
+                '>' => tokens.push(Token::Right(1 + Tokenizer::count_run(&mut chars, '>'))),
+                '+' => Tokenizer::push_counted_run(
+                    &mut tokens,
+                    1 + Tokenizer::count_run(&mut chars, '+'),
+                    Token::Plus,
+                ),
+                '-' => Tokenizer::push_counted_run(
+                    &mut tokens,
+                    1 + Tokenizer::count_run(&mut chars, '-'),
+                    Token::Minus,
+                ),
+                '.' => tokens.push(Token::Dot),
+                ',' => tokens.push(Token::Comma),
+                '[' => tokens.push(Token::BracketOpen),
+                ']' => tokens.push(Token::BracketClose),
+                _ => {}
+            }
+        }
+
+        return tokens;
+    }
+
+    /// Consumes and counts any further characters matching `target`, leaving
+    /// the iterator positioned on the first non-matching character.
+    fn count_run(chars: &mut std::iter::Peekable<std::slice::Iter<char>>, target: char) -> usize {
+        let mut count = 0;
+        while chars.peek() == Some(&&target) {
+            chars.next();
+            count += 1;
+        }
+        return count;
+    }
+
+    /// Splits a run longer than `u8::MAX` into multiple tokens so the count
+    /// never overflows the `u8` the arithmetic tokens carry.
+    fn push_counted_run(tokens: &mut Vec<Token>, mut count: usize, make: fn(u8) -> Token) {
+        while count > 0 {
+            let chunk = count.min(u8::MAX as usize);
+            tokens.push(make(chunk as u8));
+            count -= chunk;
+        }
     }
 }
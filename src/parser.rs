@@ -0,0 +1,55 @@
+//! Turns the flat, run-length-encoded token stream into a tree of statements
+//! where each `[...]` is an explicit `Loop` node owning its body. This lets
+//! `Brain::run` execute by walking the tree instead of juggling a program
+//! counter and a jump table.
+
+use crate::Token;
+
+#[derive(Debug)]
+pub enum Statement {
+    IncrementPointer(usize),
+    DecrementPointer(usize),
+    Increment(u8),
+    Decrement(u8),
+    Input,
+    Output,
+    Loop(Vec<Statement>),
+}
+
+pub fn parse(tokens: &[Token]) -> Result<Vec<Statement>, String> {
+    let mut index = 0;
+    let statements = parse_block(tokens, &mut index)?;
+    if index != tokens.len() {
+        return Err(format!("Unmatched ']' at instruction {index}"));
+    }
+    return Ok(statements);
+}
+
+/// Parses statements until it runs out of tokens or hits a `]`, which it
+/// leaves for the caller to consume (or to report as unmatched).
+fn parse_block(tokens: &[Token], index: &mut usize) -> Result<Vec<Statement>, String> {
+    let mut statements = Vec::new();
+    while *index < tokens.len() {
+        match &tokens[*index] {
+            Token::BracketClose => return Ok(statements),
+            Token::BracketOpen => {
+                *index += 1;
+                let body = parse_block(tokens, index)?;
+                if *index >= tokens.len() {
+                    return Err("Unmatched '[': missing closing bracket".to_string());
+                }
+                *index += 1;
+                statements.push(Statement::Loop(body));
+                continue;
+            }
+            Token::Left(count) => statements.push(Statement::DecrementPointer(*count)),
+            Token::Right(count) => statements.push(Statement::IncrementPointer(*count)),
+            Token::Plus(count) => statements.push(Statement::Increment(*count)),
+            Token::Minus(count) => statements.push(Statement::Decrement(*count)),
+            Token::Dot => statements.push(Statement::Output),
+            Token::Comma => statements.push(Statement::Input),
+        }
+        *index += 1;
+    }
+    return Ok(statements);
+}